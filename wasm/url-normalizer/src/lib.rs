@@ -1,12 +1,24 @@
 use wasm_bindgen::prelude::*;
-use url::Url;
-use std::collections::HashSet;
+use url::{Host, Url};
+use std::collections::{HashMap, HashSet};
 
 /// URL归一化器 - 清洗和去重URL
 #[wasm_bindgen]
 pub struct UrlNormalizer {
     // 追踪参数黑名单
     tracking_params: HashSet<String>,
+    // 域名别名表（from -> to），用于折叠镜像/区域域名
+    host_aliases: HashMap<String, String>,
+    // 是否对保留的查询参数做规范排序（少数站点对顺序敏感，可关闭）
+    sort_query: bool,
+    // 国际化域名是否输出 Unicode 显示形式（默认 false，即 ASCII/punycode）
+    idna_unicode: bool,
+}
+
+impl Default for UrlNormalizer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[wasm_bindgen]
@@ -27,8 +39,71 @@ impl UrlNormalizer {
         for param in common_trackers {
             tracking_params.insert(param.to_string());
         }
-        
-        Self { tracking_params }
+
+        let mut host_aliases = HashMap::new();
+        for from in ["twitter.com", "www.twitter.com", "mobile.twitter.com"] {
+            host_aliases.insert(from.to_string(), "x.com".to_string());
+        }
+        for from in ["www.reddit.com", "old.reddit.com", "new.reddit.com"] {
+            host_aliases.insert(from.to_string(), "reddit.com".to_string());
+        }
+
+        Self { tracking_params, host_aliases, sort_query: true, idna_unicode: false }
+    }
+
+    /// 设置国际化域名是否输出 Unicode 显示形式（默认 false，即 ASCII/punycode）
+    #[wasm_bindgen(setter)]
+    pub fn set_idna_unicode(&mut self, idna_unicode: bool) {
+        self.idna_unicode = idna_unicode;
+    }
+
+    /// 国际化域名是否输出 Unicode 显示形式
+    #[wasm_bindgen(getter)]
+    pub fn idna_unicode(&self) -> bool {
+        self.idna_unicode
+    }
+
+    /// 用给定的追踪参数列表构造归一化器（替换默认黑名单）
+    #[wasm_bindgen]
+    pub fn with_tracking_params(params: Vec<JsValue>) -> Self {
+        let mut normalizer = Self::new();
+        normalizer.tracking_params.clear();
+        for param in params {
+            if let Some(name) = param.as_string() {
+                normalizer.tracking_params.insert(name);
+            }
+        }
+        normalizer
+    }
+
+    /// 新增一个追踪参数
+    #[wasm_bindgen]
+    pub fn add_tracking_param(&mut self, name: &str) {
+        self.tracking_params.insert(name.to_string());
+    }
+
+    /// 移除一个追踪参数
+    #[wasm_bindgen]
+    pub fn remove_tracking_param(&mut self, name: &str) {
+        self.tracking_params.remove(name);
+    }
+
+    /// 新增一条域名别名规则（from -> to）
+    #[wasm_bindgen]
+    pub fn add_host_alias(&mut self, from: &str, to: &str) {
+        self.host_aliases.insert(from.to_string(), to.to_string());
+    }
+
+    /// 设置是否对查询参数排序（默认开启）
+    #[wasm_bindgen(setter)]
+    pub fn set_sort_query(&mut self, sort_query: bool) {
+        self.sort_query = sort_query;
+    }
+
+    /// 是否对查询参数排序
+    #[wasm_bindgen(getter)]
+    pub fn sort_query(&self) -> bool {
+        self.sort_query
     }
     
     /// 归一化单个URL
@@ -40,6 +115,32 @@ impl UrlNormalizer {
         }
     }
     
+    /// 将相对链接解析为绝对URL后再归一化
+    ///
+    /// 用 `base` 作为基准解析 `href`（支持 `/foo`、`../bar`、`?page=2` 等相对形式），
+    /// 解析失败时按现有的吞错约定返回原始 `href`。
+    #[wasm_bindgen]
+    pub fn resolve(&self, base: &str, href: &str) -> String {
+        match self.resolve_internal(base, href) {
+            Ok(normalized) => normalized,
+            Err(_) => href.to_string(),
+        }
+    }
+
+    /// 批量解析相对链接并归一化
+    #[wasm_bindgen]
+    pub fn resolve_batch(&self, base: &str, hrefs: Vec<JsValue>) -> Vec<String> {
+        let mut result = Vec::new();
+
+        for href_val in hrefs {
+            if let Some(href) = href_val.as_string() {
+                result.push(self.resolve(base, &href));
+            }
+        }
+
+        result
+    }
+
     /// 批量归一化并去重
     #[wasm_bindgen]
     pub fn normalize_batch(&self, urls: Vec<JsValue>) -> Vec<String> {
@@ -88,7 +189,49 @@ impl UrlNormalizer {
     }
 }
 
+/// 判断字节是否为 RFC 3986 未保留字符（ALPHA / DIGIT / `-` / `.` / `_` / `~`）
+fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~')
+}
+
+/// 规范化字符串中的百分号编码：扫描 `%XY`，解码两位十六进制，
+/// 若解码字节为未保留字符则还原为字面量，否则重新输出 `%` 加大写后的十六进制。
+fn canonicalize_percent_encoding(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hi = (bytes[i + 1] as char).to_digit(16);
+            let lo = (bytes[i + 2] as char).to_digit(16);
+            if let (Some(hi), Some(lo)) = (hi, lo) {
+                let decoded = (hi * 16 + lo) as u8;
+                if is_unreserved(decoded) {
+                    out.push(decoded);
+                } else {
+                    out.push(b'%');
+                    out.push(bytes[i + 1].to_ascii_uppercase());
+                    out.push(bytes[i + 2].to_ascii_uppercase());
+                }
+                i += 3;
+                continue;
+            }
+        }
+        // 逐字节拷贝，保留任何非 ASCII 字节序列原样，绝不按 char 截断
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
 impl UrlNormalizer {
+    /// 内部相对链接解析逻辑：先 join，再走归一化管线
+    fn resolve_internal(&self, base: &str, href: &str) -> Result<String, url::ParseError> {
+        let base_url = Url::parse(base)?;
+        let absolute = base_url.join(href)?;
+        self.normalize_internal(absolute.as_str())
+    }
+
     /// 内部归一化逻辑
     fn normalize_internal(&self, url_str: &str) -> Result<String, url::ParseError> {
         let mut parsed = Url::parse(url_str)?;
@@ -97,45 +240,153 @@ impl UrlNormalizer {
         if parsed.scheme() == "http" {
             let _ = parsed.set_scheme("https");
         }
+
+        // 1b. 去除与协议默认端口一致的端口（:443 for https, :80 for http）
+        if let Some(port) = parsed.port() {
+            let default = match parsed.scheme() {
+                "https" => Some(443),
+                "http" => Some(80),
+                _ => None,
+            };
+            if default == Some(port) {
+                let _ = parsed.set_port(None);
+            }
+        }
         
         // 2. 规范化域名
-        let normalized_host = match parsed.host_str() {
-            Some("twitter.com") | Some("www.twitter.com") | Some("mobile.twitter.com") => Some("x.com"),
-            Some("www.reddit.com") | Some("old.reddit.com") | Some("new.reddit.com") => Some("reddit.com"),
-            _ => None,
-        };
-        
+        let normalized_host = parsed
+            .host_str()
+            .and_then(|host| self.host_aliases.get(host).cloned());
+
         if let Some(new_host) = normalized_host {
-            let _ = parsed.set_host(Some(new_host));
+            let _ = parsed.set_host(Some(&new_host));
         }
-        
+
         // 3. 移除追踪参数
-        let clean_query: Vec<(String, String)> = parsed
+        let mut clean_query: Vec<(String, String)> = parsed
             .query_pairs()
             .filter(|(key, _)| !self.tracking_params.contains(key.as_ref()))
             .map(|(k, v)| (k.to_string(), v.to_string()))
             .collect();
-        
+
+        // 规范化查询参数顺序，使 ?a=1&b=2 与 ?b=2&a=1 归一化为同一字符串。
+        // 按 key 的字节序做稳定排序，保证多值参数（tag=a&tag=b）保持输入相对顺序。
+        if self.sort_query {
+            clean_query.sort_by(|(a, _), (b, _)| a.as_bytes().cmp(b.as_bytes()));
+        }
+
         if clean_query.is_empty() {
             parsed.set_query(None);
         } else {
-            let query_str = clean_query
-                .iter()
-                .map(|(k, v)| format!("{}={}", k, v))
-                .collect::<Vec<_>>()
-                .join("&");
-            parsed.set_query(Some(&query_str));
+            // 先让 url 负责重新百分号编码（query_pairs 已解码，不能直接拼回），
+            // 再在已编码的 ASCII 形式上做百分号规范化。
+            {
+                let mut serializer = parsed.query_pairs_mut();
+                serializer.clear();
+                for (k, v) in &clean_query {
+                    serializer.append_pair(k, v);
+                }
+            }
+            let canonical_query = canonicalize_percent_encoding(parsed.query().unwrap_or(""));
+            parsed.set_query(Some(&canonical_query));
         }
-        
+
         // 4. 移除fragment (锚点)
         parsed.set_fragment(None);
         
-        // 5. 移除尾部斜杠
+        // 5. 规范化路径中的百分号编码（大写十六进制、还原未保留字符）
+        let canonical_path = canonicalize_percent_encoding(parsed.path());
+        parsed.set_path(&canonical_path);
+
+        // 6. 移除尾部斜杠
         let path = parsed.path().to_string();
         if path.ends_with('/') && path.len() > 1 {
             parsed.set_path(path.trim_end_matches('/'));
         }
-        
+
+        // 7. 国际化域名归一化。
+        // `url` 在解析 special-scheme（http/https）时已把 `Host::Domain` 编码为 punycode，
+        // 所以 ASCII 规范形式天然就是 Unicode 与 xn-- 两种输入的共同归一化结果。
+        // 当调用方显式要求 Unicode 显示形式时，`set_host` 会把 Unicode 再次编回 punycode，
+        // 因此必须在最终序列化字符串里替换 host 片段，而不能回灌到 `Url`。
+        // IP 字面量不是 `Host::Domain`，保持不变。
+        if self.idna_unicode {
+            if let Some(Host::Domain(domain)) = parsed.host() {
+                let unicode = idna::domain_to_unicode(domain).0;
+                if unicode != domain {
+                    return Ok(replace_host_in_authority(parsed.as_str(), domain, &unicode));
+                }
+            }
+        }
+
         Ok(parsed.to_string())
     }
 }
+
+/// 在序列化后的 URL 里，把权限段（authority）中的 ASCII host 替换为给定形式。
+/// 只定位 host 本身的跨度（跳过 `userinfo@` 前缀与 `:port` 后缀），
+/// 避免误伤路径/查询乃至 userinfo 中的同名子串。
+fn replace_host_in_authority(url_str: &str, ascii_host: &str, new_host: &str) -> String {
+    if let Some(scheme_pos) = url_str.find("://") {
+        let auth_start = scheme_pos + 3;
+        let auth_end = url_str[auth_start..]
+            .find('/')
+            .map(|p| auth_start + p)
+            .unwrap_or(url_str.len());
+        // host 起点：userinfo 分隔符 `@` 之后（若有）
+        let host_start = url_str[auth_start..auth_end]
+            .rfind('@')
+            .map(|p| auth_start + p + 1)
+            .unwrap_or(auth_start);
+        // host 终点：端口分隔符 `:` 之前（若有）
+        let host_end = url_str[host_start..auth_end]
+            .find(':')
+            .map(|p| host_start + p)
+            .unwrap_or(auth_end);
+        if &url_str[host_start..host_end] == ascii_host {
+            return format!("{}{}{}", &url_str[..host_start], new_host, &url_str[host_end..]);
+        }
+        url_str.to_string()
+    } else {
+        url_str.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unicode_and_punycode_hosts_collapse() {
+        let normalizer = UrlNormalizer::new();
+        let unicode = normalizer.normalize("https://例え.jp/path");
+        let puny = normalizer.normalize("https://xn--r8jz45g.jp/path");
+        assert_eq!(unicode, puny);
+        assert!(unicode.contains("xn--r8jz45g.jp"));
+    }
+
+    #[test]
+    fn idna_unicode_flag_emits_unicode_host() {
+        let mut normalizer = UrlNormalizer::new();
+        normalizer.set_idna_unicode(true);
+        let out = normalizer.normalize("https://xn--r8jz45g.jp/path");
+        assert!(out.contains("例え.jp"));
+        assert!(!out.contains("xn--"));
+    }
+
+    #[test]
+    fn unicode_host_replacement_skips_userinfo() {
+        let mut normalizer = UrlNormalizer::new();
+        normalizer.set_idna_unicode(true);
+        // punycode 串同时出现在 userinfo 与 host，只应替换真正的 host
+        let out = normalizer.normalize("https://xn--r8jz45g.jp@xn--r8jz45g.jp/path");
+        assert_eq!(out, "https://xn--r8jz45g.jp@例え.jp/path");
+    }
+
+    #[test]
+    fn ip_literal_host_is_untouched() {
+        let mut normalizer = UrlNormalizer::new();
+        normalizer.set_idna_unicode(true);
+        assert_eq!(normalizer.normalize("https://127.0.0.1/a"), "https://127.0.0.1/a");
+    }
+}